@@ -1,5 +1,5 @@
 use futures::future::{self, BoxFuture, FutureExt};
-use http_service::{HttpService, Response};
+use http_service::{ConnectionInfo, HttpService, Response};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 struct Server {
@@ -22,7 +22,7 @@ impl HttpService for Server {
     type ConnectionFuture = future::Ready<Result<(), std::io::Error>>;
     type ResponseFuture = BoxFuture<'static, Result<http_service::Response, std::io::Error>>;
 
-    fn connect(&self) -> Self::ConnectionFuture {
+    fn connect(&self, _info: &ConnectionInfo) -> Self::ConnectionFuture {
         future::ok(())
     }
 
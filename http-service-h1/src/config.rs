@@ -0,0 +1,45 @@
+//! Per-server timeout configuration.
+
+use std::time::Duration;
+
+/// Timeouts applied to every connection a [`Server`](crate::Server) accepts.
+///
+/// All fields default to `None`, meaning no timeout is enforced — this matches the
+/// crate's behavior before `ServiceConfig` existed, so adopting it is opt-in.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceConfig {
+    keep_alive: Option<Duration>,
+    client_request_timeout: Option<Duration>,
+}
+
+impl ServiceConfig {
+    /// Create a config with no timeouts set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long an idle keep-alive connection may sit between requests before the
+    /// server closes it.
+    pub fn keep_alive(mut self, duration: Duration) -> Self {
+        self.keep_alive = Some(duration);
+        self
+    }
+
+    /// How long the server will wait, once a request's headers have been parsed, for
+    /// the request to be read and responded to before dropping the connection.
+    ///
+    /// Time spent receiving the headers is not counted here; it falls under
+    /// [`keep_alive`](Self::keep_alive).
+    pub fn client_request_timeout(mut self, duration: Duration) -> Self {
+        self.client_request_timeout = Some(duration);
+        self
+    }
+
+    pub(crate) fn keep_alive_duration(&self) -> Option<Duration> {
+        self.keep_alive
+    }
+
+    pub(crate) fn client_request_timeout_duration(&self) -> Option<Duration> {
+        self.client_request_timeout
+    }
+}
@@ -0,0 +1,45 @@
+//! The error type returned when a connection's dispatch loop ends abnormally.
+
+use std::fmt;
+
+use async_std::io;
+use http_service::Error;
+
+/// Why a connection stopped being served.
+///
+/// Replaces the opaque `io::ErrorKind::Other` this crate used to collapse every
+/// failure into, so callers can tell a service error apart from a malformed request or
+/// a transport failure.
+#[derive(Debug)]
+pub enum DispatchError {
+    /// The `HttpService` itself returned an error, either from `connect` or `respond`.
+    Service(Error),
+    /// A transport-level I/O error.
+    Io(io::Error),
+    /// The incoming request could not be parsed as HTTP.
+    Parse,
+    /// A configured timeout elapsed before the request completed.
+    Timeout,
+    /// An error occurred while upgrading the connection.
+    Upgrade,
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchError::Service(e) => write!(f, "service error: {}", e),
+            DispatchError::Io(e) => write!(f, "I/O error: {}", e),
+            DispatchError::Parse => write!(f, "malformed request"),
+            DispatchError::Timeout => write!(f, "timed out"),
+            DispatchError::Upgrade => write!(f, "error upgrading connection"),
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+impl From<io::Error> for DispatchError {
+    fn from(err: io::Error) -> Self {
+        DispatchError::Io(err)
+    }
+}
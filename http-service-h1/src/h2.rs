@@ -0,0 +1,166 @@
+//! HTTP/2 dispatch for connections that negotiated `h2` (via ALPN or `h2c` upgrade).
+
+use std::convert::TryInto;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_std::io::{Read, Write};
+use async_std::prelude::*;
+use bytes::Bytes;
+use futures::stream::TryStreamExt;
+use http_service::{ConnectionInfo, HttpService};
+
+use crate::DispatchError;
+
+/// Which protocol a connection should be driven with.
+///
+/// Decided once per connection, right after the (optional) TLS handshake, and used to
+/// pick the dispatch loop in [`crate::accept`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Protocol {
+    /// Plain HTTP/1.1, dispatched via `async-h1`.
+    Http1,
+    /// HTTP/2, dispatched via the `h2` crate.
+    Http2,
+}
+
+impl Protocol {
+    /// Select a protocol from the ALPN value negotiated during a TLS handshake, if any.
+    pub fn from_alpn(info: &ConnectionInfo) -> Self {
+        match info.alpn_protocol() {
+            Some("h2") => Protocol::Http2,
+            _ => Protocol::Http1,
+        }
+    }
+}
+
+/// Drive a single connection that has already been determined to speak HTTP/2.
+///
+/// Performs the connection preface and then multiplexes every concurrent stream onto
+/// `service.respond`, sharing the single `conn` value across all of them (it's `Clone`,
+/// same as the `async-h1` path).
+pub(crate) async fn serve<S, RW>(
+    service: Arc<S>,
+    conn: S::Connection,
+    io: RW,
+) -> Result<(), DispatchError>
+where
+    S: HttpService,
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+    <S as HttpService>::Connection: Sync,
+    RW: Read + Write + Unpin + Send + Sync + 'static,
+{
+    let mut h2_conn = h2::server::handshake(io)
+        .await
+        .map_err(|_| DispatchError::Upgrade)?;
+
+    while let Some(result) = h2_conn.accept().await {
+        let (req, respond) = result.map_err(|_| DispatchError::Parse)?;
+        let service = service.clone();
+        let conn = conn.clone();
+        async_std::task::spawn(async move {
+            let _ = dispatch_stream(service, conn, req, respond).await;
+        });
+    }
+
+    Ok(())
+}
+
+/// A `Stream` of the `Bytes` chunks in an `h2::RecvStream`, releasing flow-control
+/// capacity as each chunk is handed off so the peer keeps sending the rest of the body.
+struct RecvStreamBody(h2::RecvStream);
+
+impl futures::Stream for RecvStreamBody {
+    type Item = std::io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.0).poll_data(cx) {
+            Poll::Ready(Some(Ok(bytes))) => {
+                let _ = self.0.flow_control().release_capacity(bytes.len());
+                Poll::Ready(Some(Ok(bytes)))
+            }
+            Poll::Ready(Some(Err(e))) => {
+                Poll::Ready(Some(Err(std::io::Error::new(std::io::ErrorKind::Other, e))))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Size of the chunks the response body is read into before being handed to
+/// `h2::SendStream::send_data`.
+const SEND_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Write `body` to `send_stream`, one `SEND_CHUNK_SIZE` chunk at a time, waiting for
+/// flow-control capacity before each `send_data` call.
+///
+/// The flow-control window `poll_capacity` grants can be (and by default, for anything
+/// over ~64KB, will be) smaller than the chunk just read, so each chunk is sent in as
+/// many `available`-sized pieces as the window allows rather than assuming one
+/// `reserve_capacity`/`poll_capacity` round trip covers the whole chunk.
+async fn send_body(
+    mut send_stream: h2::SendStream<Bytes>,
+    mut body: http_service::Body,
+) -> Result<(), DispatchError> {
+    let mut chunk = [0u8; SEND_CHUNK_SIZE];
+    loop {
+        let n = body.read(&mut chunk).await.map_err(DispatchError::Io)?;
+        if n == 0 {
+            send_stream
+                .send_data(Bytes::new(), true)
+                .map_err(|_| DispatchError::Upgrade)?;
+            return Ok(());
+        }
+
+        let mut sent = 0;
+        while sent < n {
+            send_stream.reserve_capacity(n - sent);
+            let available = futures::future::poll_fn(|cx| send_stream.poll_capacity(cx))
+                .await
+                .ok_or(DispatchError::Upgrade)?
+                .map_err(|_| DispatchError::Upgrade)?;
+
+            let end = sent + available.min(n - sent);
+            let data = Bytes::copy_from_slice(&chunk[sent..end]);
+            send_stream
+                .send_data(data, false)
+                .map_err(|_| DispatchError::Upgrade)?;
+            sent = end;
+        }
+    }
+}
+
+async fn dispatch_stream<S>(
+    service: Arc<S>,
+    conn: S::Connection,
+    req: http::Request<h2::RecvStream>,
+    mut respond: h2::server::SendResponse<Bytes>,
+) -> Result<(), DispatchError>
+where
+    S: HttpService,
+{
+    // `h2::RecvStream` hands out `Bytes` chunks through `poll_data`/flow control rather
+    // than implementing `AsyncRead` directly, so `RecvStreamBody` bridges it to a
+    // `Stream` first and `into_async_read` does the rest — the same shape
+    // `http-service-hyper` uses to adapt `hyper::Body`.
+    let (parts, recv_body) = req.into_parts();
+    let body = http_service::Body::from_reader(RecvStreamBody(recv_body).into_async_read(), None);
+    let req = http::Request::from_parts(parts, body);
+    let req: http_service::Request = req.try_into().map_err(|_| DispatchError::Parse)?;
+
+    let res = service
+        .respond(conn, req)
+        .await
+        .map_err(|e| DispatchError::Service(e.into()))?;
+
+    let (parts, body) = http::Response::from(res).into_parts();
+    let response = http::Response::from_parts(parts, ());
+    let send_stream = respond
+        .send_response(response, false)
+        .map_err(|_| DispatchError::Upgrade)?;
+
+    send_body(send_stream, body).await
+}
@@ -7,21 +7,63 @@
 
 use std::future::Future;
 
-use http_service::{Error, HttpService};
+use http_service::{ConnectionInfo, HttpService};
+
+mod config;
+mod error;
+mod h2;
+mod tls;
+
+pub use config::ServiceConfig;
+pub use error::DispatchError;
+pub use h2::Protocol;
+pub use tls::{Acceptor, TlsServer};
+#[cfg(feature = "async-tls")]
+pub use tls::{AsyncTlsAcceptor, TlsStream};
 
 use async_std::io::{self, Read, Write};
 use async_std::net::SocketAddr;
 use async_std::prelude::*;
 use async_std::stream::Stream;
-use async_std::sync::Arc;
+use async_std::sync::{Arc, Mutex};
 use async_std::task::{Context, Poll};
+use futures::future::{self, Either};
 use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// A stream that can describe its own transport-level metadata.
+///
+/// Implemented for the stream types this crate knows how to introspect (currently
+/// `TcpStream`, and `TlsStream` with the `async-tls` feature); other streams (e.g. Unix sockets) get an empty [`ConnectionInfo`]
+/// via the default body.
+pub trait Peer {
+    /// Metadata about this connection's transport.
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo::new()
+    }
+}
+
+impl Peer for async_std::net::TcpStream {
+    fn connection_info(&self) -> ConnectionInfo {
+        let mut info = ConnectionInfo::new();
+        if let Ok(addr) = self.peer_addr() {
+            info.set_peer_addr(addr);
+        }
+        if let Ok(addr) = self.local_addr() {
+            info.set_local_addr(addr);
+        }
+        info
+    }
+}
+
+impl Peer for UnixStreamWrapper {}
 
 /// A listening HTTP server that accepts connections in HTTP1.
 #[derive(Debug)]
 pub struct Server<I, S: HttpService> {
     incoming: I,
     service: Arc<S>,
+    config: ServiceConfig,
 }
 
 impl<I, RW, S> Server<I, S>
@@ -29,7 +71,7 @@ where
     S: HttpService,
     <<S as HttpService>::ResponseFuture as Future>::Output: Send,
     <S as HttpService>::Connection: Sync,
-    RW: Read + Write + Clone + Unpin + Send + Sync + 'static,
+    RW: Read + Write + Clone + Unpin + Send + Sync + Peer + 'static,
     I: Stream<Item = io::Result<RW>> + Unpin + Send + Sync,
 {
     /// Consume this [`Builder`], creating a [`Server`].
@@ -59,49 +101,270 @@ where
         Server {
             service: Arc::new(service),
             incoming,
+            config: ServiceConfig::default(),
         }
     }
 
+    /// Set the timeouts this server enforces on every connection it accepts.
+    pub fn with_config(mut self, config: ServiceConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Run the server forever-ish.
     pub async fn run(&mut self) -> io::Result<()> {
         while let Some(read_write) = self.incoming.next().await {
             let read_write = read_write?;
-            async_std::task::spawn(accept(self.service.clone(), read_write));
+            let service = self.service.clone();
+            let config = self.config.clone();
+            async_std::task::spawn(async move {
+                if let Err(err) = accept(service, read_write, config).await {
+                    log::error!("connection dropped: {}", err);
+                }
+            });
         }
 
         Ok(())
     }
 }
 
+/// Whether a request is asking to upgrade the connection out of HTTP/1.1, e.g. via
+/// `Connection: Upgrade` (WebSocket) or `CONNECT`.
+fn wants_upgrade(req: &http_service::Request) -> bool {
+    req.method() == http_types::Method::Connect
+        || req
+            .header("Connection")
+            .map(|values| values.iter().any(|v| v.as_str().eq_ignore_ascii_case("upgrade")))
+            .unwrap_or(false)
+}
+
+/// Whether a cleartext HTTP/1.1 request is asking to upgrade straight to HTTP/2 (`h2c`).
+fn wants_h2c_upgrade(req: &http_service::Request) -> bool {
+    req.header("Upgrade")
+        .map(|values| values.iter().any(|v| v.as_str().eq_ignore_ascii_case("h2c")))
+        .unwrap_or(false)
+}
+
+/// Whether a request is asking the server to confirm it wants the body before the
+/// client sends it.
+fn wants_100_continue(req: &http_service::Request) -> bool {
+    req.header("Expect")
+        .map(|values| values.iter().any(|v| v.as_str().eq_ignore_ascii_case("100-continue")))
+        .unwrap_or(false)
+}
+
+/// A header-only copy of `req` (method, URL, headers — no body), for handing to
+/// [`HttpService::on_upgrade`] after the original request's body has already been
+/// consumed by [`HttpService::respond`] to build the handshake response.
+fn request_head(req: &http_service::Request) -> http_service::Request {
+    let mut copy = http_types::Request::new(req.method(), req.url().clone());
+    for (name, values) in req.iter() {
+        for value in values.iter() {
+            copy.append_header(name, value);
+        }
+    }
+    copy
+}
+
+/// What a connection's keep-alive loop decided to hand off once `async_h1` is done
+/// with the socket.
+enum Upgrade<C> {
+    /// Switch this connection to HTTP/2 via a cleartext `h2c` upgrade.
+    H2c,
+    /// Hand the connection to [`HttpService::on_upgrade`].
+    Connection(C, http_service::Request),
+}
+
+/// Which half of the request/response cycle a connection is presently in, and since
+/// when — used by [`watch_phase`] to apply `client_request_timeout` and `keep_alive` as
+/// two independently-reset timeouts instead of one timeout wrapping the whole
+/// `async_h1::accept` keep-alive loop.
+#[derive(Clone, Copy)]
+enum Phase {
+    /// Waiting for the next request to start arriving; bounded by `keep_alive`.
+    Idle(Instant),
+    /// A request is being read and responded to; bounded by `client_request_timeout`.
+    Reading(Instant),
+}
+
+/// How often [`watch_phase`] re-checks `phase` against whichever timeout currently
+/// applies to it.
+const PHASE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Resolve once whichever of `client_request_timeout`/`keep_alive` applies to `phase`'s
+/// current value has elapsed since `phase` last changed; never resolves if neither
+/// timeout is configured.
+async fn watch_phase(phase: Arc<Mutex<Phase>>, config: ServiceConfig) {
+    loop {
+        async_std::task::sleep(PHASE_POLL_INTERVAL).await;
+        let (since, limit) = match *phase.lock().await {
+            Phase::Idle(since) => (since, config.keep_alive_duration()),
+            Phase::Reading(since) => (since, config.client_request_timeout_duration()),
+        };
+        if limit.map_or(false, |limit| since.elapsed() >= limit) {
+            return;
+        }
+    }
+}
+
 /// Accept a new connection.
-async fn accept<S, RW>(service: Arc<S>, read_write: RW) -> Result<(), Error>
+async fn accept<S, RW>(
+    service: Arc<S>,
+    read_write: RW,
+    config: ServiceConfig,
+) -> Result<(), DispatchError>
 where
     S: HttpService,
     <<S as HttpService>::ResponseFuture as Future>::Output: Send,
     <S as HttpService>::Connection: Sync,
-    RW: Read + Write + Unpin + Clone + Send + Sync + 'static,
+    RW: Read + Write + Unpin + Clone + Send + Sync + Peer + 'static,
 {
+    let info = read_write.connection_info();
     let conn = service
         .clone()
-        .connect()
+        .connect(&info)
         .await
-        .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
+        .map_err(|e| DispatchError::Service(e.into()))?;
+
+    if Protocol::from_alpn(&info) == Protocol::Http2 {
+        return h2::serve(service, conn, read_write).await;
+    }
 
-    async_h1::accept(read_write, |req| async {
+    // If a request on this connection turns out to want to upgrade (WebSocket,
+    // `CONNECT`, or a cleartext `h2c` upgrade), the closure below stashes what's
+    // needed here instead of handing the socket to `on_upgrade`/`h2::serve`
+    // immediately. It also asks `async_h1` to close the connection after writing the
+    // handshake response, so that by the time `serve_fut` resolves below,
+    // `async_h1` is done reading and writing `read_write` — only then is it safe to
+    // start a second, independent reader/writer on the same socket.
+    let upgrade: Arc<Mutex<Option<Upgrade<S::Connection>>>> = Arc::new(Mutex::new(None));
+
+    // `async_h1::accept` loops internally to serve every request on this keep-alive
+    // connection, so there's no single `await` point to wrap in a timeout for "this one
+    // request took too long" vs. "this connection has sat idle too long" — both phases
+    // happen inside the same opaque call. `phase` is how the request closure reports
+    // which of the two it's currently in, so `watch_phase` below can apply whichever of
+    // `client_request_timeout`/`keep_alive` is relevant right now, independently reset
+    // every time the connection moves between the two.
+    let phase = Arc::new(Mutex::new(Phase::Idle(Instant::now())));
+
+    let serve_fut = async_h1::accept(read_write.clone(), |req| {
         let conn = conn.clone();
         let service = service.clone();
+        let upgrade = upgrade.clone();
+        let phase = phase.clone();
         async move {
-            let res = service
-                .respond(conn, req)
-                .await
-                .map_err(|_| io::Error::from(io::ErrorKind::Other))?;
-            Ok(res)
+            *phase.lock().await = Phase::Reading(Instant::now());
+            let result = async {
+                if wants_h2c_upgrade(&req) {
+                    *upgrade.lock().await = Some(Upgrade::H2c);
+                    let mut res = http_types::Response::new(http_types::StatusCode::SwitchingProtocols);
+                    res.insert_header("Connection", "Upgrade");
+                    res.insert_header("Upgrade", "h2c");
+                    // Same reasoning as the generic upgrade path below: tell `async_h1`
+                    // to stop looping for more requests on this socket once it's
+                    // written this response, so `h2::serve` below is the only thing
+                    // reading/writing it.
+                    res.append_header("Connection", "close");
+                    return Ok(res);
+                }
+
+                if wants_upgrade(&req) {
+                    let req_for_upgrade = request_head(&req);
+                    let is_connect = req.method() == http_types::Method::Connect;
+                    let conn_for_upgrade = conn.clone();
+                    // The handshake response (e.g. `Sec-WebSocket-Accept`, or the 2xx
+                    // that confirms a CONNECT tunnel) is the service's to construct,
+                    // same as any other response — it's only the socket handoff
+                    // afterward that's special.
+                    let mut res = service
+                        .respond(conn, req)
+                        .await
+                        .map_err(|e| io::Error::new(io::ErrorKind::Other, DispatchError::Service(e.into())))?;
+                    // A CONNECT tunnel succeeds with a 2xx status (conventionally
+                    // "200 Connection established"), not 101 — 101 only applies to the
+                    // `Connection: Upgrade` style of upgrade (WebSocket, `h2c`).
+                    let upgrade_succeeded = if is_connect {
+                        res.status().is_success()
+                    } else {
+                        res.status() == http_types::StatusCode::SwitchingProtocols
+                    };
+                    if upgrade_succeeded {
+                        *upgrade.lock().await = Some(Upgrade::Connection(conn_for_upgrade, req_for_upgrade));
+                        // `append_header` rather than `insert_header`, so this adds to
+                        // rather than clobbers whatever `Connection` value the
+                        // handshake response already set (e.g. the `Connection:
+                        // Upgrade` RFC 6455 requires on a WebSocket 101).
+                        res.append_header("Connection", "close");
+                    }
+                    return Ok(res);
+                }
+
+                if wants_100_continue(&req) {
+                    if let Err(rejection) = service.expect(&req).await {
+                        return Ok(rejection);
+                    }
+                    // Otherwise fall through to `respond` as usual; `async-h1` is
+                    // responsible for having already written the `100 Continue`
+                    // interim response before the body starts arriving.
+                }
+
+                let res = service
+                    .respond(conn, req)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, DispatchError::Service(e.into())))?;
+                Ok(res)
+            }
+            .await;
+            *phase.lock().await = Phase::Idle(Instant::now());
+            result
         }
-        .await
-    })
-    .await?;
+    });
+
+    let result = if config.client_request_timeout_duration().is_some() || config.keep_alive_duration().is_some() {
+        match future::select(Box::pin(serve_fut), Box::pin(watch_phase(phase, config))).await {
+            Either::Left((result, _)) => result,
+            Either::Right(((), _)) => Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")),
+        }
+    } else {
+        serve_fut.await
+    };
+
+    result.map_err(dispatch_error_from_io)?;
 
-    Ok(())
+    // Only now that `async_h1` has returned ownership of `read_write` — either the
+    // peer disconnected, or (for an upgrade) the closure above asked it to close the
+    // connection after writing the handshake response — is it safe to start reading
+    // and writing the socket again.
+    match upgrade.lock().await.take() {
+        Some(Upgrade::H2c) => h2::serve(service, conn, read_write).await,
+        Some(Upgrade::Connection(conn, req)) => {
+            service.on_upgrade(conn, req, Box::pin(read_write)).await;
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Recover the [`DispatchError`] `async_h1`'s closure raised, from the `io::Error` it
+/// comes back out as.
+///
+/// `async_h1`'s per-request callback can only return `io::Error`, so a
+/// [`DispatchError::Service`] raised by [`HttpService::respond`] is smuggled through it
+/// as an `io::Error` whose inner source is the `DispatchError` itself; this unwraps that
+/// back out rather than letting it flatten into an opaque [`DispatchError::Io`], so
+/// callers can still tell "the service returned an error" from "the transport broke."
+fn dispatch_error_from_io(err: io::Error) -> DispatchError {
+    if err.kind() == io::ErrorKind::TimedOut {
+        return DispatchError::Timeout;
+    }
+    match err.into_inner() {
+        Some(source) => match source.downcast::<DispatchError>() {
+            Ok(dispatch_err) => *dispatch_err,
+            Err(source) => DispatchError::Io(io::Error::new(io::ErrorKind::Other, source)),
+        },
+        None => DispatchError::Io(io::Error::new(io::ErrorKind::Other, "unknown I/O error")),
+    }
 }
 
 /// Serve the given `HttpService` at the given address, using `async-h1` as backend, and return a
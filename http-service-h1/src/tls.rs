@@ -0,0 +1,240 @@
+//! A pluggable TLS-termination layer in front of [`Server`](crate::Server).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_std::io::{self, Read, Write};
+use async_std::stream::Stream;
+use async_std::sync::{Mutex, MutexGuardArc};
+use async_std::task::{Context, Poll};
+use http_service::{ConnectionInfo, HttpService};
+
+use crate::{accept, Peer, ServiceConfig};
+
+/// Terminates TLS on a raw, freshly-accepted stream.
+///
+/// Implementations wrap a concrete TLS library (`async-tls`/`rustls`, `native-tls`, ...)
+/// behind this crate's transport-agnostic `Server`. `Self::Io` is whatever decrypted,
+/// bidirectional stream the handshake produces.
+pub trait Acceptor<RW>: Send + Sync + 'static {
+    /// The decrypted stream produced by a successful handshake.
+    type Io: Read + Write + Clone + Unpin + Send + Sync + Peer + 'static;
+    /// The handshake future returned by `accept`.
+    type AcceptFuture: Future<Output = io::Result<Self::Io>> + Send + 'static;
+
+    /// Perform the TLS handshake on `raw`, returning a decrypted stream once it
+    /// completes.
+    fn accept(&self, raw: RW) -> Self::AcceptFuture;
+}
+
+/// A listening server that terminates TLS via an [`Acceptor`] before handing the
+/// decrypted connection to the same per-connection dispatch [`Server`] uses.
+#[derive(Debug)]
+pub struct TlsServer<A, I, S: HttpService> {
+    incoming: I,
+    acceptor: Arc<A>,
+    service: Arc<S>,
+    config: ServiceConfig,
+}
+
+impl<A, I, RW, S> TlsServer<A, I, S>
+where
+    S: HttpService,
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+    <S as HttpService>::Connection: Sync,
+    RW: Send + 'static,
+    A: Acceptor<RW>,
+    I: Stream<Item = io::Result<RW>> + Unpin + Send + Sync,
+{
+    /// Compose an [`Acceptor`] with the given inner service.
+    pub fn new(incoming: I, acceptor: A, service: S) -> Self {
+        TlsServer {
+            incoming,
+            acceptor: Arc::new(acceptor),
+            service: Arc::new(service),
+            config: ServiceConfig::default(),
+        }
+    }
+
+    /// Set the timeouts this server enforces on every connection it accepts.
+    pub fn with_config(mut self, config: ServiceConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Run the server forever-ish, performing a TLS handshake on each incoming
+    /// connection before dispatching it.
+    pub async fn run(&mut self) -> io::Result<()> {
+        use async_std::prelude::*;
+
+        while let Some(raw) = self.incoming.next().await {
+            let raw = raw?;
+            let acceptor = self.acceptor.clone();
+            let service = self.service.clone();
+            let config = self.config.clone();
+            async_std::task::spawn(async move {
+                match acceptor.accept(raw).await {
+                    Ok(io) => {
+                        if let Err(err) = accept(service, io, config).await {
+                            log::error!("connection dropped: {}", err);
+                        }
+                    }
+                    Err(err) => log::error!("TLS handshake failed: {}", err),
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// An [`Acceptor`] backed by `async-tls` (rustls under the hood).
+///
+/// Populates [`ConnectionInfo::alpn_protocol`] from the negotiated protocol so it can
+/// feed the [HTTP/2 selection path](crate::Protocol::from_alpn).
+#[cfg(feature = "async-tls")]
+#[derive(Clone)]
+pub struct AsyncTlsAcceptor(async_tls::TlsAcceptor);
+
+#[cfg(feature = "async-tls")]
+impl AsyncTlsAcceptor {
+    /// Wrap an already-configured `async_tls::TlsAcceptor`.
+    pub fn new(acceptor: async_tls::TlsAcceptor) -> Self {
+        AsyncTlsAcceptor(acceptor)
+    }
+}
+
+#[cfg(feature = "async-tls")]
+impl<RW> Acceptor<RW> for AsyncTlsAcceptor
+where
+    RW: Read + Write + Clone + Unpin + Send + Sync + Peer + 'static,
+{
+    type Io = TlsStream<RW>;
+    type AcceptFuture = Pin<Box<dyn Future<Output = io::Result<Self::Io>> + Send>>;
+
+    fn accept(&self, raw: RW) -> Self::AcceptFuture {
+        let acceptor = self.0.clone();
+        Box::pin(async move {
+            let info = raw.connection_info();
+            let stream = acceptor.accept(raw).await?;
+            let alpn_protocol = stream
+                .get_ref()
+                .1
+                .get_alpn_protocol()
+                .map(|p| String::from_utf8_lossy(p).into_owned());
+            let mut info = info;
+            if let Some(protocol) = alpn_protocol {
+                info.set_alpn_protocol(protocol);
+            }
+            Ok(TlsStream {
+                inner: Arc::new(Mutex::new(stream)),
+                read_lock: Arc::new(std::sync::Mutex::new(None)),
+                write_lock: Arc::new(std::sync::Mutex::new(None)),
+                info,
+            })
+        })
+    }
+}
+
+/// The future behind a single in-flight `lock_arc()` call, boxed so `TlsStream` doesn't
+/// need to name `async_std`'s internal future type.
+#[cfg(feature = "async-tls")]
+type LockFuture<RW> =
+    Pin<Box<dyn Future<Output = MutexGuardArc<async_tls::server::TlsStream<RW>>> + Send>>;
+
+/// A `Clone`-able handle to a single TLS connection's decrypted stream.
+///
+/// `async_tls`'s stream type requires `&mut` access for reads and writes, so this
+/// serializes concurrent clones behind a mutex — read and write each keep their own
+/// in-flight lock future (see [`poll_locked`]) since a full-duplex caller (e.g. split
+/// read/write halves) genuinely drives reads and writes concurrently, even though a
+/// single direction is never driven by more than one task at a time.
+#[cfg(feature = "async-tls")]
+#[derive(Clone)]
+pub struct TlsStream<RW> {
+    inner: Arc<Mutex<async_tls::server::TlsStream<RW>>>,
+    read_lock: Arc<std::sync::Mutex<Option<LockFuture<RW>>>>,
+    write_lock: Arc<std::sync::Mutex<Option<LockFuture<RW>>>>,
+    info: ConnectionInfo,
+}
+
+#[cfg(feature = "async-tls")]
+impl<RW> std::fmt::Debug for TlsStream<RW> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsStream").finish()
+    }
+}
+
+#[cfg(feature = "async-tls")]
+impl<RW> Peer for TlsStream<RW> {
+    fn connection_info(&self) -> ConnectionInfo {
+        self.info.clone()
+    }
+}
+
+/// Drive `slot`'s lock future to a guard and apply `op` to the locked stream.
+///
+/// Reusing the same boxed future across polls (instead of building and single-polling a
+/// fresh one every call) keeps its waker registration alive while
+/// the mutex is contended, so a `Pending` here actually gets woken once the lock frees up
+/// rather than stalling indefinitely.
+#[cfg(feature = "async-tls")]
+fn poll_locked<RW, T>(
+    slot: &std::sync::Mutex<Option<LockFuture<RW>>>,
+    inner: &Arc<Mutex<async_tls::server::TlsStream<RW>>>,
+    cx: &mut Context<'_>,
+    op: impl FnOnce(Pin<&mut async_tls::server::TlsStream<RW>>, &mut Context<'_>) -> Poll<io::Result<T>>,
+) -> Poll<io::Result<T>>
+where
+    RW: Read + Write + Unpin + Send + 'static,
+{
+    let mut slot = slot.lock().unwrap();
+    let fut = slot.get_or_insert_with(|| Box::pin(inner.clone().lock_arc()));
+    match fut.as_mut().poll(cx) {
+        Poll::Ready(mut guard) => {
+            *slot = None;
+            drop(slot);
+            op(Pin::new(&mut *guard), cx)
+        }
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+#[cfg(feature = "async-tls")]
+impl<RW: Read + Write + Unpin + Send + 'static> Read for TlsStream<RW> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_locked(&self.read_lock, &self.inner, cx, |stream, cx| {
+            stream.poll_read(cx, buf)
+        })
+    }
+}
+
+#[cfg(feature = "async-tls")]
+impl<RW: Read + Write + Unpin + Send + 'static> Write for TlsStream<RW> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_locked(&self.write_lock, &self.inner, cx, |stream, cx| {
+            stream.poll_write(cx, buf)
+        })
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        poll_locked(&self.write_lock, &self.inner, cx, |stream, cx| {
+            stream.poll_flush(cx)
+        })
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        poll_locked(&self.write_lock, &self.inner, cx, |stream, cx| {
+            stream.poll_close(cx)
+        })
+    }
+}
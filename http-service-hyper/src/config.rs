@@ -0,0 +1,35 @@
+//! Per-server timeout configuration.
+
+use std::time::Duration;
+
+/// Timeouts applied to every connection a [`Server`](crate::Server) accepts.
+///
+/// All fields default to `None`, meaning no timeout is enforced — this matches the
+/// crate's behavior before `ServiceConfig` existed, so adopting it is opt-in.
+///
+/// `keep_alive` is the only setting, and this backend applies it as the TCP keepalive
+/// interval of accepted sockets (see [`keep_alive`](Self::keep_alive)). There is no bound
+/// on how long a client may take to finish sending a request, or on how long to wait for
+/// a client to disconnect after the response has been written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServiceConfig {
+    keep_alive: Option<Duration>,
+}
+
+impl ServiceConfig {
+    /// Create a config with no timeouts set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the TCP keepalive interval of accepted sockets. A connection that stays
+    /// reachable but sends no further requests is not closed by this setting.
+    pub fn keep_alive(mut self, duration: Duration) -> Self {
+        self.keep_alive = Some(duration);
+        self
+    }
+
+    pub(crate) fn keep_alive_duration(&self) -> Option<Duration> {
+        self.keep_alive
+    }
+}
@@ -0,0 +1,45 @@
+//! The error type returned when dispatching a request through [`WrapConnection`](crate).
+
+use std::fmt;
+use std::io;
+
+use http_service::Error;
+
+/// Why dispatching a request to the wrapped `HttpService` failed.
+///
+/// Replaces the opaque `io::ErrorKind::Other` this crate used to collapse every
+/// `respond` failure into, so callers can tell a service error apart from a transport
+/// or protocol failure.
+#[derive(Debug)]
+pub enum DispatchError {
+    /// The `HttpService` itself returned an error.
+    Service(Error),
+    /// A transport-level I/O error.
+    Io(io::Error),
+    /// The request could not be parsed.
+    Parse,
+    /// A configured timeout elapsed before the request completed.
+    Timeout,
+    /// An error occurred while upgrading the connection.
+    Upgrade,
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchError::Service(e) => write!(f, "service error: {}", e),
+            DispatchError::Io(e) => write!(f, "I/O error: {}", e),
+            DispatchError::Parse => write!(f, "malformed request"),
+            DispatchError::Timeout => write!(f, "timed out"),
+            DispatchError::Upgrade => write!(f, "error upgrading connection"),
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+impl From<io::Error> for DispatchError {
+    fn from(err: io::Error) -> Self {
+        DispatchError::Io(err)
+    }
+}
@@ -18,6 +18,12 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{self, Poll};
 
+mod config;
+mod error;
+
+pub use config::ServiceConfig;
+pub use error::DispatchError;
+
 // Wrapper type to allow us to provide a blanket `Service` impl
 struct WrapConnection<H: HttpService> {
     service: Arc<H>,
@@ -29,15 +35,18 @@ where
     H: HttpService,
 {
     type Response = http::Response<hyper::Body>;
-    type Error = std::io::Error;
+    type Error = DispatchError;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
 
     fn call(&mut self, req: http::Request<hyper::Body>) -> Self::Future {
         // Convert Request
-        let error = std::io::Error::from(std::io::ErrorKind::Other);
         let req_hyper: http::Request<Body> = req.map(|body| {
             use futures::stream::TryStreamExt;
-            let body_stream = body.map(|chunk| chunk.map(|c| c.to_vec()).map_err(|_| error));
+            let body_stream = body.map(|chunk| {
+                chunk.map(|c| c.to_vec()).map_err(|e| {
+                    std::io::Error::new(std::io::ErrorKind::Other, e)
+                })
+            });
             let body_reader = body_stream.into_async_read();
             Body::from_reader(body_reader, None)
         });
@@ -47,7 +56,10 @@ where
 
         // Convert Request
         let fut = async {
-            let res: http_types::Response = fut.into_future().await.map_err(|_| error)?;
+            let res: http_types::Response = fut
+                .into_future()
+                .await
+                .map_err(|e| DispatchError::Service(e.into()))?;
             let res_hyper = hyper::Response::<Body>::from(res);
 
             let (parts, body) = res_hyper.into_parts();
@@ -105,6 +117,14 @@ impl<I: TryStream, Sp> Builder<I, Sp> {
         }
     }
 
+    /// Apply the given [`ServiceConfig`] to every connection this server accepts.
+    pub fn with_config(self, config: ServiceConfig) -> Builder<I, Sp> {
+        let keep_alive = config.keep_alive_duration();
+        Builder {
+            inner: self.inner.tcp_keepalive(keep_alive),
+        }
+    }
+
     /// Consume this [`Builder`], creating a [`Server`].
     ///
     /// # Examples
@@ -0,0 +1,50 @@
+//! Configuration for how response bodies are encoded on the way back to Lambda.
+
+/// Controls how a response body is encoded before being handed back to API Gateway,
+/// an Application Load Balancer, or a Lambda Function URL.
+///
+/// By default, a body with a `Content-Type` the config doesn't recognize as binary
+/// falls back to the old heuristic: it's sent as `LambdaBody::Text` if it happens to
+/// be valid UTF-8, and base64-encoded `LambdaBody::Binary` otherwise. Registering a
+/// media type with [`binary_media_type`](Self::binary_media_type) skips that guess
+/// for any response declaring it, mirroring API Gateway's own `binaryMediaTypes`
+/// setting.
+#[derive(Clone, Debug, Default)]
+pub struct ServiceConfig {
+    binary_media_types: Vec<String>,
+}
+
+/// `Content-Type` essences that are always encoded as binary, even without being
+/// registered via [`ServiceConfig::binary_media_type`].
+const DEFAULT_BINARY_MEDIA_TYPES: &[&str] = &["application/octet-stream", "application/pdf", "application/zip"];
+
+impl ServiceConfig {
+    /// Create a config with no additional binary media types registered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Always encode a response whose `Content-Type` matches `media_type` as
+    /// `LambdaBody::Binary`, instead of guessing from the bytes.
+    pub fn binary_media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.binary_media_types.push(media_type.into());
+        self
+    }
+
+    /// Whether a response with the given `Content-Type` should be encoded as binary
+    /// without consulting the UTF-8 heuristic.
+    ///
+    /// Returns `None` when `content_type` is absent, leaving the caller to fall back
+    /// to the UTF-8 heuristic.
+    pub(crate) fn wants_binary(&self, content_type: Option<&str>) -> Option<bool> {
+        let content_type = content_type?;
+        let essence = content_type.split(';').next().unwrap_or(content_type).trim();
+        let top_level_binary =
+            essence.starts_with("image/") || essence.starts_with("audio/") || essence.starts_with("video/");
+        Some(
+            top_level_binary
+                || DEFAULT_BINARY_MEDIA_TYPES.contains(&essence)
+                || self.binary_media_types.iter().any(|t| t == essence),
+        )
+    }
+}
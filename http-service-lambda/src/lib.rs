@@ -8,6 +8,13 @@
 //! run on lambda and processing events from API Gateway or ALB without much
 //! change.
 //!
+//! # Streaming responses
+//!
+//! There is no streaming entry point — every invocation is driven through
+//! [`dispatch`], which `read_to_end`s the whole response body before handing it back
+//! to the Lambda runtime. No bytes reach the caller until the whole body has been
+//! read, and the whole body is held in memory at once.
+//!
 //! # Examples
 //!
 //! **Hello World**
@@ -25,158 +32,266 @@
 #![warn(missing_docs, missing_doc_code_examples)]
 #![cfg_attr(test, deny(warnings))]
 
-use futures::{
-    channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
-    AsyncReadExt, Future, FutureExt, StreamExt, TryFutureExt,
-};
-use http_service::{Body as HttpBody, HttpService, Request as HttpRequest};
-use lambda_http::{lambda, Body as LambdaBody, Handler, Request as LambdaHttpRequest};
-use lambda_runtime::{error::HandlerError, Context};
-use std::{
-    sync::mpsc::{channel as sync_channel, Sender as SyncSender},
-    thread,
-};
+use futures::{AsyncReadExt, Future, FutureExt};
+use http_service::{Body as HttpBody, ConnectionInfo, HttpService, Request as HttpRequest};
+use lambda_http::{Body as LambdaBody, Request as LambdaHttpRequest, RequestContext, RequestExt};
+use lambda_runtime::{Context, Error, LambdaEvent};
+use serde::Deserialize;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
 use tokio::runtime::Runtime as TokioRuntime;
+use tower_service::Service;
+
+mod config;
+mod websocket;
+
+pub use config::ServiceConfig;
+pub use websocket::WebSocketRequestContext;
+
+use websocket::WebSocketEvent;
 
 type LambdaResponse = lambda_http::Response<LambdaBody>;
 
-trait ResultExt<Ok, Error> {
-    fn handler_error(self, description: &str) -> Result<Ok, HandlerError>;
+/// The shape of a single Lambda invocation payload this crate accepts.
+///
+/// REST and HTTP API Gateway proxy requests, ALB target group requests, and Lambda
+/// Function URL requests (which reuse the HTTP API v2 payload format) all
+/// deserialize as [`LambdaHttpRequest`]. API Gateway WebSocket `$connect` /
+/// `$disconnect` / `$default` events have a different shape — no HTTP method or path,
+/// just a route key and connection id — so they get their own variant, handled by
+/// [`WebSocketEvent`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LambdaInput {
+    Http(LambdaHttpRequest),
+    WebSocket(WebSocketEvent),
 }
 
-impl<Ok, Error> ResultExt<Ok, Error> for Result<Ok, Error> {
-    fn handler_error(self, description: &str) -> Result<Ok, HandlerError> {
-        self.map_err(|_| HandlerError::from(description))
-    }
-}
+/// Accessors for the Lambda invocation metadata this crate attaches to every request
+/// it converts, so a wrapped `HttpService` can read the request ID, function ARN,
+/// deadline, or API Gateway/ALB request-context fields for tracing, deadline-aware
+/// timeouts, or authorizer claim handling.
+pub trait LambdaRequestExt {
+    /// The `Context` for the Lambda invocation that produced this request.
+    fn lambda_context(&self) -> Option<Context>;
 
-type RequestSender = UnboundedSender<(LambdaHttpRequest, ResponseSender)>;
-type RequestReceiver = UnboundedReceiver<(LambdaHttpRequest, ResponseSender)>;
-type ResponseSender = SyncSender<Result<LambdaResponse, HandlerError>>;
+    /// The API Gateway / ALB request-context metadata carried alongside this request.
+    fn lambda_request_context(&self) -> Option<RequestContext>;
 
-struct Server<S> {
-    service: S,
-    requests: RequestReceiver,
+    /// The API Gateway WebSocket metadata carried alongside this request, if it was
+    /// converted from a `$connect` / `$disconnect` / `$default` WebSocket event.
+    fn websocket_context(&self) -> Option<WebSocketRequestContext>;
 }
 
-impl<S: HttpService> Server<S> {
-    fn new(service: S, requests: RequestReceiver) -> Server<S> {
-        Server { service, requests }
+impl LambdaRequestExt for HttpRequest {
+    fn lambda_context(&self) -> Option<Context> {
+        self.ext::<Context>().cloned()
+    }
+
+    fn lambda_request_context(&self) -> Option<RequestContext> {
+        self.ext::<RequestContext>().cloned()
     }
 
-    async fn run(mut self) -> Result<(), ()> {
-        while let Some((req, reply)) = self.requests.next().await {
-            let response = self.serve(req).await;
-            reply.send(response).unwrap();
+    fn websocket_context(&self) -> Option<WebSocketRequestContext> {
+        self.ext::<WebSocketRequestContext>().cloned()
+    }
+}
+
+/// Adapts an `HttpService` into the [`tower_service::Service`] that the Lambda
+/// runtime drives directly, one invocation at a time.
+///
+/// Earlier versions of this crate bridged the synchronous `lambda_http::Handler`
+/// trait by forwarding each invocation over an unbounded channel to a `Server` task
+/// running on a background thread, then blocking on a second, `std::sync::mpsc`
+/// channel for the reply. `lambda_runtime::run` now drives an async `Service`
+/// directly, so `call` can just `await` `connect`/`respond` inline on the same task
+/// the runtime polls.
+struct HttpServiceAdapter<S> {
+    service: Arc<S>,
+    config: ServiceConfig,
+}
+
+impl<S> HttpServiceAdapter<S> {
+    fn new(service: S, config: ServiceConfig) -> Self {
+        HttpServiceAdapter {
+            service: Arc::new(service),
+            config,
         }
-        Ok(())
-    }
-
-    async fn serve(&self, req: LambdaHttpRequest) -> Result<LambdaResponse, HandlerError> {
-        // Create new connection
-        let mut connection = self
-            .service
-            .connect()
-            .into_future()
-            .await
-            .handler_error("connect")?;
-
-        // Convert Lambda request to HTTP request
-        let req: HttpRequest = req.map(|b| match b {
-            LambdaBody::Binary(v) => HttpBody::from(v),
-            LambdaBody::Text(s) => HttpBody::from(s.into_bytes()),
-            LambdaBody::Empty => HttpBody::empty(),
-        });
-
-        // Handle request
-        let (parts, mut body) = self
-            .service
-            .respond(&mut connection, req)
-            .into_future()
-            .await
-            .handler_error("respond")?
-            .into_parts();
-
-        // Convert response back to Lambda response
-        let mut buf = Vec::new();
-        body.read_to_end(&mut buf).await.handler_error("body")?;
-        let lambda_body = if buf.is_empty() {
-            LambdaBody::Empty
-        } else {
-            match String::from_utf8(buf) {
-                Ok(s) => LambdaBody::Text(s),
-                Err(b) => LambdaBody::Binary(b.into_bytes()),
-            }
-        };
-        Ok(LambdaResponse::from_parts(parts, lambda_body))
     }
 }
 
-struct ProxyHandler(RequestSender);
+impl<S: HttpService> Service<LambdaEvent<LambdaInput>> for HttpServiceAdapter<S>
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    type Response = LambdaResponse;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
 
-impl Handler<LambdaResponse> for ProxyHandler {
-    fn run(
-        &mut self,
-        event: LambdaHttpRequest,
-        _ctx: Context,
-    ) -> Result<LambdaResponse, HandlerError> {
-        let (reply, response_chan) = sync_channel();
-        self.0
-            .unbounded_send((event, reply))
-            .handler_error("forward event")?;
-        response_chan.recv().handler_error("receive response")?
+    fn call(&mut self, event: LambdaEvent<LambdaInput>) -> Self::Future {
+        let service = self.service.clone();
+        let config = self.config.clone();
+        Box::pin(async move { dispatch(&*service, event, &config).await })
     }
 }
 
-fn prepare_proxy<S: HttpService>(
-    service: S,
-) -> (ProxyHandler, impl Future<Output = Result<(), ()>>) {
-    let (request_sender, requests) = unbounded();
-    let server = Server::new(service, requests);
-    (ProxyHandler(request_sender), server.run())
+/// Drive a single Lambda invocation through `service`, converting the Lambda request
+/// into an `HttpRequest`, awaiting the service's response, reading its body in full with
+/// `read_to_end`, and converting the result back into a single `LambdaResponse` — the
+/// traditional buffered Lambda invocation model requires the whole response up front,
+/// so there's no way to start returning bytes to the caller before the body finishes.
+///
+/// The assembled bytes are then handed to `config` to decide whether they go back as
+/// `LambdaBody::Text` or base64-encoded `LambdaBody::Binary`.
+async fn dispatch<S: HttpService>(
+    service: &S,
+    event: LambdaEvent<LambdaInput>,
+    config: &ServiceConfig,
+) -> Result<LambdaResponse, Error>
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    let LambdaEvent { payload: input, context: ctx } = event;
+
+    // Create new connection
+    let connection = service
+        .connect(&ConnectionInfo::new())
+        .await
+        .map_err(|e| Error::from(e.into().to_string()))?;
+
+    // Convert the Lambda invocation payload into an `HttpRequest`, attaching
+    // whichever request-context extension its event type carries.
+    let mut req: HttpRequest = match input {
+        LambdaInput::Http(req) => {
+            let request_context = req.request_context();
+            let mut req: HttpRequest = req.map(|b| match b {
+                LambdaBody::Binary(v) => HttpBody::from(v),
+                LambdaBody::Text(s) => HttpBody::from(s.into_bytes()),
+                LambdaBody::Empty => HttpBody::empty(),
+            });
+            req.set_ext(request_context);
+            req
+        }
+        LambdaInput::WebSocket(event) => {
+            let (mut req, websocket_context) = event.into_http_request()?;
+            req.set_ext(websocket_context);
+            req
+        }
+    };
+    req.set_ext(ctx);
+
+    // Handle request
+    let (mut parts, mut body) = service
+        .respond(connection, req)
+        .await
+        .map_err(|e| Error::from(e.into().to_string()))?
+        .into_parts();
+
+    let mut buf = Vec::new();
+    body.read_to_end(&mut buf)
+        .await
+        .map_err(|e| Error::from(e.to_string()))?;
+
+    let content_type = parts
+        .headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok());
+    let wants_binary = config.wants_binary(content_type);
+
+    let lambda_body = if buf.is_empty() {
+        LambdaBody::Empty
+    } else if wants_binary == Some(true) {
+        LambdaBody::Binary(buf)
+    } else {
+        // Either the `Content-Type` wasn't declared, or it was but `config` doesn't
+        // consider it binary — either way, fall back to the UTF-8 heuristic.
+        match String::from_utf8(buf) {
+            Ok(s) => LambdaBody::Text(s),
+            Err(b) => LambdaBody::Binary(b.into_bytes()),
+        }
+    };
+    Ok(LambdaResponse::from_parts(parts, lambda_body))
 }
 
-/// Serve the given `HttpService` using `lambda_http` as backend and
+/// Serve the given `HttpService` using `lambda_runtime` as backend and
 /// return a `Future` that can be `await`ed on.
-pub fn serve<S: HttpService>(s: S) -> impl Future<Output = Result<(), ()>> {
-    let (handler, server_task) = prepare_proxy(s);
-    thread::spawn(|| lambda!(handler));
-    server_task
+pub async fn serve<S: HttpService>(s: S) -> Result<(), Error>
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    serve_with_config(s, ServiceConfig::default()).await
+}
+
+/// Like [`serve`], but with a [`ServiceConfig`] controlling how response bodies are
+/// encoded.
+pub async fn serve_with_config<S: HttpService>(s: S, config: ServiceConfig) -> Result<(), Error>
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    lambda_runtime::run(HttpServiceAdapter::new(s, config)).await
 }
 
 /// Run the given `HttpService` on the default runtime, using
-/// `lambda_http` as backend.
-pub fn run<S: HttpService>(s: S) {
-    let (handler, server) = prepare_proxy(s);
-    let mut runtime = TokioRuntime::new().expect("Can not start tokio runtime");
-    runtime.spawn(server.boxed().compat());
-    lambda!(handler, runtime);
+/// `lambda_runtime` as backend.
+pub fn run<S: HttpService>(s: S)
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    run_with_config(s, ServiceConfig::default())
+}
+
+/// Like [`run`], but with a [`ServiceConfig`] controlling how response bodies are
+/// encoded.
+pub fn run_with_config<S: HttpService>(s: S, config: ServiceConfig)
+where
+    <<S as HttpService>::ResponseFuture as Future>::Output: Send,
+{
+    let runtime = TokioRuntime::new().expect("Can not start tokio runtime");
+    runtime
+        .block_on(serve_with_config(s, config))
+        .expect("lambda runtime encountered an error");
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use futures::future;
-    use lambda_http::Handler;
 
     struct DummyService;
 
     impl HttpService for DummyService {
         type Connection = ();
-        type ConnectionFuture = future::Ready<Result<(), ()>>;
-        type ResponseFuture = future::BoxFuture<'static, Result<http_service::Response, ()>>;
-        fn connect(&self) -> Self::ConnectionFuture {
+        type ConnectionError = std::convert::Infallible;
+        type ConnectionFuture = future::Ready<Result<(), std::convert::Infallible>>;
+        type ResponseError = std::convert::Infallible;
+        type ResponseFuture =
+            future::BoxFuture<'static, Result<http_service::Response, std::convert::Infallible>>;
+
+        fn connect(&self, _info: &ConnectionInfo) -> Self::ConnectionFuture {
             future::ok(())
         }
-        fn respond(&self, _conn: &mut (), _req: http_service::Request) -> Self::ResponseFuture {
+
+        fn respond(&self, _conn: (), _req: http_service::Request) -> Self::ResponseFuture {
             Box::pin(async move { Ok(http_service::Response::new(http_service::Body::empty())) })
         }
     }
 
-    fn run_once(request: LambdaHttpRequest) -> Result<LambdaResponse, HandlerError> {
-        let (mut handler, server) = prepare_proxy(DummyService);
-        std::thread::spawn(|| futures::executor::block_on(server));
-        handler.run(request, Context::default())
+    fn run_once(input: LambdaInput) -> Result<LambdaResponse, Error> {
+        let mut adapter = HttpServiceAdapter::new(DummyService, ServiceConfig::default());
+        let event = LambdaEvent {
+            payload: input,
+            context: Context::default(),
+        };
+        futures::executor::block_on(adapter.call(event))
+    }
+
+    fn run_once_http(request: LambdaHttpRequest) -> Result<LambdaResponse, Error> {
+        run_once(LambdaInput::Http(request))
     }
 
     #[test]
@@ -185,7 +300,7 @@ mod tests {
         // https://docs.aws.amazon.com/lambda/latest/dg/eventsources.html#eventsources-api-gateway-request
         let input = include_str!("../tests/data/apigw_proxy_request.json");
         let request = lambda_http::request::from_str(input).unwrap();
-        let result = run_once(request);
+        let result = run_once_http(request);
         assert!(
             result.is_ok(),
             format!("event was not handled as expected {:?}", result)
@@ -198,10 +313,72 @@ mod tests {
         // https://docs.aws.amazon.com/elasticloadbalancing/latest/application/lambda-functions.html#multi-value-headers
         let input = include_str!("../tests/data/alb_request.json");
         let request = lambda_http::request::from_str(input).unwrap();
-        let result = run_once(request);
+        let result = run_once_http(request);
         assert!(
             result.is_ok(),
             format!("event was not handled as expected {:?}", result)
         );
     }
+
+    #[test]
+    fn handle_function_url_request() {
+        // Lambda Function URLs reuse the API Gateway HTTP API v2 payload format, so
+        // this goes through the same `LambdaHttpRequest` path as `handle_apigw_request`.
+        let input = include_str!("../tests/data/function_url_request.json");
+        let request = lambda_http::request::from_str(input).unwrap();
+        let result = run_once_http(request);
+        assert!(
+            result.is_ok(),
+            format!("event was not handled as expected {:?}", result)
+        );
+    }
+
+    #[test]
+    fn handle_websocket_connect_request() {
+        let input = include_str!("../tests/data/apigw_websocket_connect_request.json");
+        let event = websocket::WebSocketEvent::from_str(input).unwrap();
+        let result = run_once(LambdaInput::WebSocket(event));
+        assert!(
+            result.is_ok(),
+            format!("event was not handled as expected {:?}", result)
+        );
+    }
+
+    #[test]
+    fn handle_websocket_message_request() {
+        let input = include_str!("../tests/data/apigw_websocket_message_request.json");
+        let event = websocket::WebSocketEvent::from_str(input).unwrap();
+        let result = run_once(LambdaInput::WebSocket(event));
+        assert!(
+            result.is_ok(),
+            format!("event was not handled as expected {:?}", result)
+        );
+    }
+
+    #[test]
+    fn handle_websocket_disconnect_request() {
+        let input = include_str!("../tests/data/apigw_websocket_disconnect_request.json");
+        let event = websocket::WebSocketEvent::from_str(input).unwrap();
+        let result = run_once(LambdaInput::WebSocket(event));
+        assert!(
+            result.is_ok(),
+            format!("event was not handled as expected {:?}", result)
+        );
+    }
+
+    #[test]
+    fn websocket_payload_resolves_to_websocket_variant_through_lambda_input() {
+        // The tests above deserialize straight into `WebSocketEvent`, which proves
+        // `WebSocketEvent` itself can parse the fixture but says nothing about whether
+        // `LambdaInput`'s `#[serde(untagged)]` resolution — what `lambda_runtime::run`
+        // actually does with every invocation payload — falls through to the
+        // `WebSocket` variant rather than having the more permissive
+        // `LambdaHttpRequest` deserializer silently absorb it first.
+        let input = include_str!("../tests/data/apigw_websocket_connect_request.json");
+        let parsed: LambdaInput = serde_json::from_str(input).unwrap();
+        assert!(
+            matches!(parsed, LambdaInput::WebSocket(_)),
+            "a WebSocket payload must resolve to LambdaInput::WebSocket, not LambdaInput::Http"
+        );
+    }
 }
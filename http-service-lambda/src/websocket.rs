@@ -0,0 +1,93 @@
+//! Mapping for API Gateway WebSocket `$connect` / `$disconnect` / `$default` events.
+//!
+//! These arrive as a different JSON shape entirely from the REST/HTTP API Gateway
+//! proxy, ALB, and Lambda Function URL requests `lambda_http::Request` already
+//! understands (there's no HTTP method or path — just a route key and a connection
+//! id), so this crate parses them with its own, much smaller, `serde` type and maps
+//! them onto an `HttpRequest` by hand.
+
+use http_service::{Body as HttpBody, Request as HttpRequest};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The API Gateway WebSocket metadata for a single `$connect` / `$disconnect` /
+/// `$default` invocation.
+///
+/// Exposed to a wrapped `HttpService` via [`LambdaRequestExt::websocket_context`](
+/// crate::LambdaRequestExt::websocket_context) so it can implement per-route
+/// behavior (e.g. recording `connection_id` on `$connect` to look up later when
+/// pushing messages back through the API Gateway Management API).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSocketRequestContext {
+    /// The route selected for this invocation: `$connect`, `$disconnect`, `$default`,
+    /// or a custom route.
+    pub route_key: String,
+    /// `CONNECT`, `MESSAGE`, or `DISCONNECT`.
+    pub event_type: String,
+    /// The persistent identifier API Gateway assigned this WebSocket connection.
+    pub connection_id: String,
+    /// The API Gateway domain name the client connected to, if present.
+    pub domain_name: Option<String>,
+    /// The deployment stage the API was invoked through, if present.
+    pub stage: Option<String>,
+    /// API Gateway's identifier for this invocation, if present.
+    pub request_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebSocketEvent {
+    request_context: WebSocketRequestContext,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    body: Option<String>,
+    #[serde(default)]
+    is_base64_encoded: bool,
+}
+
+impl WebSocketEvent {
+    /// Parse a raw API Gateway WebSocket event.
+    pub(crate) fn from_str(input: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(input)
+    }
+
+    /// Convert this event into an `HttpRequest`, returning the parsed
+    /// [`WebSocketRequestContext`] alongside it so the caller can attach it as a
+    /// request extension.
+    ///
+    /// There's no real HTTP method or path for a WebSocket frame, so one is
+    /// synthesized from the route key: `$connect`/`$disconnect` map to `GET` (they
+    /// carry no body), anything else (ordinary messages, routed via `$default` or a
+    /// custom route key) maps to `POST` with the message body attached. The route
+    /// key, without its leading `$`, becomes the request path (`$connect` becomes
+    /// `/connect`), so a service can dispatch on it the same way it would dispatch on
+    /// any other route.
+    pub(crate) fn into_http_request(self) -> http_types::Result<(HttpRequest, WebSocketRequestContext)> {
+        let ctx = self.request_context;
+        let method = match ctx.route_key.as_str() {
+            "$connect" | "$disconnect" => http_types::Method::Get,
+            _ => http_types::Method::Post,
+        };
+        let host = ctx.domain_name.as_deref().unwrap_or("localhost");
+        let path = ctx.route_key.trim_start_matches('$');
+        let url = http_types::Url::parse(&format!("http://{}/{}", host, path))?;
+
+        let mut req = http_types::Request::new(method, url);
+        for (name, value) in &self.headers {
+            req.append_header(name.as_str(), value.as_str());
+        }
+
+        let body = match self.body {
+            Some(body) if self.is_base64_encoded => {
+                let bytes = base64::decode(&body).map_err(|e| http_types::Error::from_str(http_types::StatusCode::BadRequest, e.to_string()))?;
+                HttpBody::from(bytes)
+            }
+            Some(body) => HttpBody::from(body.into_bytes()),
+            None => HttpBody::empty(),
+        };
+        req.set_body(body);
+
+        Ok((req, ctx))
+    }
+}
@@ -7,7 +7,7 @@
 #![feature(futures_api, async_await)]
 
 use futures::{executor::block_on, prelude::*};
-use http_service::{HttpService, Request, Response};
+use http_service::{ConnectionInfo, HttpService, Request, Response};
 
 /// A harness for sending simulated requests to an HTTP service
 #[derive(Debug)]
@@ -18,7 +18,7 @@ pub struct TestBackend<T: HttpService> {
  
 impl<T: HttpService> TestBackend<T> {
     fn wrap(service: T) -> Result<Self, <T::ConnectionFuture as TryFuture>::Error> {
-        let connection = block_on(service.connect().into_future())?;
+        let connection = block_on(service.connect(&ConnectionInfo::new()).into_future())?;
         Ok(Self {
             service,
             connection,
@@ -7,6 +7,7 @@
 #![doc(test(attr(allow(unused_extern_crates, unused_variables))))]
 
 use std::future::Future;
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
@@ -22,6 +23,71 @@ pub type Response = http_types::Response;
 /// An HTTP compatible error type.
 pub type Error = http_types::Error;
 
+/// Metadata about the transport a connection was accepted on.
+///
+/// Backends populate this from whatever they have on hand at accept time (the raw
+/// socket, the TLS handshake) and pass it to [`HttpService::connect`], so a service can
+/// make per-connection decisions — rate limiting by peer IP, logging, routing on SNI —
+/// without the backend needing a separate, bespoke mechanism for each of them.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionInfo {
+    peer_addr: Option<SocketAddr>,
+    local_addr: Option<SocketAddr>,
+    alpn_protocol: Option<String>,
+    server_name: Option<String>,
+}
+
+impl ConnectionInfo {
+    /// Create an empty `ConnectionInfo` with no metadata set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the address of the remote peer.
+    pub fn set_peer_addr(&mut self, addr: SocketAddr) -> &mut Self {
+        self.peer_addr = Some(addr);
+        self
+    }
+
+    /// The address of the remote peer, if known.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Set the local address the connection was accepted on.
+    pub fn set_local_addr(&mut self, addr: SocketAddr) -> &mut Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// The local address the connection was accepted on, if known.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Set the protocol negotiated via ALPN during the TLS handshake.
+    pub fn set_alpn_protocol(&mut self, protocol: String) -> &mut Self {
+        self.alpn_protocol = Some(protocol);
+        self
+    }
+
+    /// The protocol negotiated via ALPN during the TLS handshake (e.g. `"h2"`), if any.
+    pub fn alpn_protocol(&self) -> Option<&str> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Set the server name the client requested via SNI.
+    pub fn set_server_name(&mut self, name: String) -> &mut Self {
+        self.server_name = Some(name);
+        self
+    }
+
+    /// The server name the client requested via SNI, if any.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+}
+
 /// An async HTTP service
 ///
 /// An instance represents a service as a whole. The associated `Conn` type
@@ -50,8 +116,10 @@ pub trait HttpService: Send + Sync + 'static {
     /// Initiate a new connection.
     ///
     /// This method is given access to the global service (`&self`), which may provide
-    /// handles to connection pools, thread pools, or other global data.
-    fn connect(&self) -> Self::ConnectionFuture;
+    /// handles to connection pools, thread pools, or other global data. `info` carries
+    /// whatever the backend knows about the underlying transport (peer address, TLS
+    /// parameters) at the time the connection was accepted.
+    fn connect(&self, info: &ConnectionInfo) -> Self::ConnectionFuture;
 
     /// Response error.
     type ResponseError: Into<Error> + Send;
@@ -68,8 +136,55 @@ pub trait HttpService: Send + Sync + 'static {
     /// The handler is given shared access to the service itself, and mutable access
     /// to the state for the connection where the request is taking place.
     fn respond(&self, conn: Self::Connection, req: Request) -> Self::ResponseFuture;
+
+    /// Take over a connection that is being upgraded out of HTTP (e.g. WebSocket,
+    /// `CONNECT` tunneling).
+    ///
+    /// Backends call this after [`respond`](HttpService::respond) has produced the
+    /// handshake response for a request that asks to upgrade the connection, and only
+    /// if that response accepts the upgrade (a `101 Switching Protocols`, or a 2xx for
+    /// `CONNECT`). `req` then carries the request's method, URL and headers, but not
+    /// its body. `io` is a raw, bidirectional handle to the underlying transport; once
+    /// this future resolves, the backend considers the connection finished and tears
+    /// it down.
+    ///
+    /// The default implementation simply drops `io`, closing the connection, so
+    /// services that don't care about upgrades are unaffected.
+    fn on_upgrade(
+        &self,
+        conn: Self::Connection,
+        req: Request,
+        io: UpgradedStream,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let _ = (conn, req);
+        Box::pin(async move { drop(io) })
+    }
+
+    /// Decide whether to accept a request that arrived with `Expect: 100-continue`,
+    /// before its body has been read off the wire.
+    ///
+    /// Returning `Ok(())` tells the backend to send the `100 Continue` interim
+    /// response and proceed to read the body and call [`respond`](HttpService::respond)
+    /// as usual. Returning `Err(response)` tells it to send `response` instead and
+    /// skip reading the body entirely — useful for rejecting an oversized or
+    /// unauthorized upload before the client sends it.
+    ///
+    /// The default implementation always accepts, which is the behavior backends had
+    /// before this hook existed.
+    fn expect(&self, req: &Request) -> Pin<Box<dyn Future<Output = Result<(), Response>> + Send>> {
+        let _ = req;
+        Box::pin(async { Ok(()) })
+    }
 }
 
+/// A bidirectional handle to the raw transport underlying an upgraded connection.
+pub trait UpgradedIo: futures_io::AsyncRead + futures_io::AsyncWrite + Send + Unpin {}
+
+impl<T: futures_io::AsyncRead + futures_io::AsyncWrite + Send + Unpin> UpgradedIo for T {}
+
+/// A type-erased, owned handle to an upgraded connection's raw transport.
+pub type UpgradedStream = Pin<Box<dyn UpgradedIo>>;
+
 impl<F, R, E> HttpService for F
 where
     F: Send + Sync + 'static + Fn(Request) -> R,
@@ -82,7 +197,7 @@ where
     type ResponseFuture = R;
     type ResponseError = E;
 
-    fn connect(&self) -> Self::ConnectionFuture {
+    fn connect(&self, _info: &ConnectionInfo) -> Self::ConnectionFuture {
         OkFuture(true)
     }
 